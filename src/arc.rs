@@ -0,0 +1,311 @@
+//! An Adaptive Replacement Cache (ARC), after Megiddo & Modha. ARC keeps
+//! two LRU lists, T1 (seen once recently) and T2 (seen at least twice), plus
+//! ghost lists B1 and B2 that remember the keys of recently evicted T1/T2
+//! entries. An adaptive target `p` shifts the split between T1 and T2 based
+//! on which ghost list is taking hits, so the cache self-tunes between
+//! recency and frequency rather than committing to one policy.
+
+use std::cmp;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+struct Node<K, T> {
+    key: K,
+    val: T,
+    prev: Option<usize>,
+    next: Option<usize>,
+}
+
+/// A slab-backed doubly-linked list ordered MRU (head) to LRU (tail),
+/// indexed by key. This is the same node/free-list design `LRUCache` uses,
+/// generalized over a payload `T` so it can back the value-bearing T1/T2
+/// lists as well as the key-only ghost lists B1/B2 (`T = ()`).
+struct LruList<K, T> {
+    index: HashMap<K, usize>,
+    nodes: Vec<Option<Node<K, T>>>,
+    free: Vec<usize>,
+    head: Option<usize>,
+    tail: Option<usize>,
+}
+
+impl<K, T> LruList<K, T>
+where
+    K: Eq + Hash + Clone,
+{
+    fn new() -> LruList<K, T> {
+        LruList {
+            index: HashMap::new(),
+            nodes: vec![],
+            free: vec![],
+            head: None,
+            tail: None,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    fn contains(&self, k: &K) -> bool {
+        self.index.contains_key(k)
+    }
+
+    fn get(&self, k: &K) -> Option<&T> {
+        let i = *self.index.get(k)?;
+        Some(&self.nodes[i].as_ref().unwrap().val)
+    }
+
+    fn unlink(&mut self, i: usize) {
+        let (prev, next) = {
+            let node = self.nodes[i].as_ref().unwrap();
+            (node.prev, node.next)
+        };
+        match prev {
+            Some(p) => self.nodes[p].as_mut().unwrap().next = next,
+            None => self.tail = next,
+        }
+        match next {
+            Some(n) => self.nodes[n].as_mut().unwrap().prev = prev,
+            None => self.head = prev,
+        }
+    }
+
+    /// Inserts `k`/`v` at the MRU end. Caller must ensure `k` is not
+    /// already present.
+    fn push_front(&mut self, k: K, v: T) {
+        let i = if let Some(i) = self.free.pop() {
+            self.nodes[i] = Some(Node {
+                key: k.clone(),
+                val: v,
+                prev: self.head,
+                next: None,
+            });
+            i
+        } else {
+            let i = self.nodes.len();
+            self.nodes.push(Some(Node {
+                key: k.clone(),
+                val: v,
+                prev: self.head,
+                next: None,
+            }));
+            i
+        };
+        if let Some(head) = self.head {
+            self.nodes[head].as_mut().unwrap().next = Some(i);
+        }
+        self.head = Some(i);
+        if self.tail.is_none() {
+            self.tail = Some(i);
+        }
+        self.index.insert(k, i);
+    }
+
+    /// Moves `k` to the MRU end without changing its value.
+    fn touch(&mut self, k: &K) {
+        let i = match self.index.get(k) {
+            Some(&i) => i,
+            None => return,
+        };
+        if self.head == Some(i) {
+            return;
+        }
+        self.unlink(i);
+        {
+            let node = self.nodes[i].as_mut().unwrap();
+            node.prev = self.head;
+            node.next = None;
+        }
+        if let Some(head) = self.head {
+            self.nodes[head].as_mut().unwrap().next = Some(i);
+        }
+        self.head = Some(i);
+        if self.tail.is_none() {
+            self.tail = Some(i);
+        }
+    }
+
+    fn remove(&mut self, k: &K) -> Option<T> {
+        let i = self.index.remove(k)?;
+        self.unlink(i);
+        let node = self.nodes[i].take().unwrap();
+        self.free.push(i);
+        Some(node.val)
+    }
+
+    /// Evicts and returns the LRU (tail) entry, if any.
+    fn pop_back(&mut self) -> Option<(K, T)> {
+        let i = self.tail?;
+        let key = self.nodes[i].as_ref().unwrap().key.clone();
+        self.remove(&key).map(|v| (key, v))
+    }
+}
+
+/// An Adaptive Replacement Cache over a fixed budget `c`, with `get`,
+/// `put` and `remove` methods taking the same arguments as those of
+/// [`LRUCache`](crate::LRUCache), for workloads where plain LRU thrashes
+/// under scans. Unlike `LRUCache::put`, `put` here returns nothing: the
+/// overwritten or evicted entry is not handed back to the caller.
+pub struct ArcCache<K, V> {
+    t1: LruList<K, V>,
+    t2: LruList<K, V>,
+    b1: LruList<K, ()>,
+    b2: LruList<K, ()>,
+    p: usize,
+    c: usize,
+}
+
+impl<K, V> ArcCache<K, V>
+where
+    K: Eq + Hash + Clone,
+{
+    pub fn new(c: usize) -> ArcCache<K, V> {
+        ArcCache {
+            t1: LruList::new(),
+            t2: LruList::new(),
+            b1: LruList::new(),
+            b2: LruList::new(),
+            p: 0,
+            c: c,
+        }
+    }
+
+    /// Looks up `k`. A hit in T1 or T2 promotes the entry to the MRU end
+    /// of T2 (it's now been seen at least twice).
+    pub fn get(&mut self, k: &K) -> Option<&V> {
+        if self.t1.contains(k) {
+            let v = self.t1.remove(k).unwrap();
+            self.t2.push_front(k.clone(), v);
+        } else if self.t2.contains(k) {
+            self.t2.touch(k);
+        } else {
+            return None;
+        }
+        self.t2.get(k)
+    }
+
+    /// Inserts `k`/`v`, running the full ARC replacement policy: ghost hits
+    /// adapt the T1/T2 target `p` before the entry lands in T2, while
+    /// genuinely new keys land in T1.
+    pub fn put(&mut self, k: &K, v: V) {
+        if self.t1.contains(k) {
+            self.t1.remove(k);
+            self.t2.push_front(k.clone(), v);
+            return;
+        }
+        if self.t2.contains(k) {
+            self.t2.remove(k);
+            self.t2.push_front(k.clone(), v);
+            return;
+        }
+        if self.b1.contains(k) {
+            let delta = cmp::max(1, self.b2.len() / cmp::max(1, self.b1.len()));
+            self.p = cmp::min(self.c, self.p + delta);
+            self.replace(k);
+            self.b1.remove(k);
+            self.t2.push_front(k.clone(), v);
+            return;
+        }
+        if self.b2.contains(k) {
+            let delta = cmp::max(1, self.b1.len() / cmp::max(1, self.b2.len()));
+            self.p = self.p.saturating_sub(delta);
+            self.replace(k);
+            self.b2.remove(k);
+            self.t2.push_front(k.clone(), v);
+            return;
+        }
+        // k is in none of the four lists.
+        let l1 = self.t1.len() + self.b1.len();
+        if l1 == self.c {
+            if self.t1.len() < self.c {
+                self.b1.pop_back();
+                self.replace(k);
+            } else {
+                self.t1.pop_back();
+            }
+        } else if l1 < self.c {
+            let total = l1 + self.t2.len() + self.b2.len();
+            if total >= self.c {
+                if total == 2 * self.c {
+                    self.b2.pop_back();
+                }
+                self.replace(k);
+            }
+        }
+        self.t1.push_front(k.clone(), v);
+    }
+
+    /// Evicts one entry from T1 or T2 into the corresponding ghost list,
+    /// per the adaptive target `p`.
+    fn replace(&mut self, k: &K) {
+        let t1_len = self.t1.len();
+        if t1_len >= 1 && (t1_len > self.p || (t1_len == self.p && self.b2.contains(k))) {
+            if let Some((key, _)) = self.t1.pop_back() {
+                self.b1.push_front(key, ());
+            }
+        } else if let Some((key, _)) = self.t2.pop_back() {
+            self.b2.push_front(key, ());
+        }
+    }
+
+    /// Removes `k` from wherever it lives (T1, T2, or the ghost lists),
+    /// returning its value if it had one.
+    pub fn remove(&mut self, k: &K) -> Option<V> {
+        if let Some(v) = self.t1.remove(k) {
+            return Some(v);
+        }
+        if let Some(v) = self.t2.remove(k) {
+            return Some(v);
+        }
+        self.b1.remove(k);
+        self.b2.remove(k);
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_get() {
+        let mut c: ArcCache<String, ()> = ArcCache::new(3);
+        assert_eq!(c.get(&"foo".to_string()), None);
+    }
+
+    #[test]
+    fn test_put_get() {
+        let mut c: ArcCache<String, ()> = ArcCache::new(3);
+        c.put(&"foo".to_string(), ());
+        c.put(&"bar".to_string(), ());
+        c.put(&"baz".to_string(), ());
+        assert_eq!(c.get(&"foo".to_string()), Some(&()));
+        assert_eq!(c.get(&"bar".to_string()), Some(&()));
+        assert_eq!(c.get(&"baz".to_string()), Some(&()));
+        assert_eq!(c.get(&"quux".to_string()), None);
+    }
+
+    #[test]
+    fn test_frequent_keys_survive_a_scan() {
+        // "hot" is read repeatedly (promoting it into T2) while a long
+        // one-shot scan churns through T1; ARC should keep "hot" cached
+        // even after the scan overflows the budget, unlike plain LRU.
+        let mut c: ArcCache<String, ()> = ArcCache::new(3);
+        c.put(&"hot".to_string(), ());
+        c.get(&"hot".to_string());
+        c.get(&"hot".to_string());
+        for i in 0..10 {
+            c.put(&format!("scan{}", i), ());
+        }
+        assert_eq!(c.get(&"hot".to_string()), Some(&()));
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut c: ArcCache<String, ()> = ArcCache::new(3);
+        c.put(&"foo".to_string(), ());
+        assert_eq!(c.remove(&"foo".to_string()), Some(()));
+        assert_eq!(c.get(&"foo".to_string()), None);
+        assert_eq!(c.remove(&"foo".to_string()), None);
+    }
+}
@@ -1,112 +1,270 @@
+use std::collections::hash_map::RandomState;
 use std::collections::HashMap;
-use std::hash::Hash;
+use std::hash::{BuildHasher, Hash};
+
+mod arc;
+pub use arc::ArcCache;
 
 struct Node<K> {
     key: K,
+    prev: Option<usize>,
     next: Option<usize>,
 }
 
 impl<K> Node<K> {
-    fn new(k: K) -> Node<K> {
-        Node { key: k, next: None }
+    fn new(k: K, prev: Option<usize>) -> Node<K> {
+        Node {
+            key: k,
+            prev: prev,
+            next: None,
+        }
     }
 }
 
-pub struct LRUCache<K, V> {
-    table: HashMap<K, (V, usize)>,
+pub struct LRUCache<K, V, S = RandomState> {
+    table: HashMap<K, (V, usize), S>,
     nodes: Vec<Node<K>>,
+    // free holds vacated slots in `nodes` so `append` can reuse them instead
+    // of growing the vector monotonically.
+    free: Vec<usize>,
     head: Option<usize>,
     tail: Option<usize>,
     size: usize,
 }
 
-impl<K, V> LRUCache<K, V>
+impl<K, V> LRUCache<K, V, RandomState>
+where
+    K: Eq + Hash + Clone,
+{
+    pub fn new(size: usize) -> LRUCache<K, V, RandomState> {
+        LRUCache::with_hasher(size, RandomState::new())
+    }
+}
+
+impl<K, V, S> LRUCache<K, V, S>
 where
     K: Eq + Hash + Clone,
+    S: BuildHasher,
 {
-    pub fn new(size: usize) -> LRUCache<K, V> {
+    pub fn with_hasher(size: usize, hasher: S) -> LRUCache<K, V, S> {
         LRUCache {
-            table: HashMap::new(),
+            table: HashMap::with_hasher(hasher),
             nodes: vec![],
+            free: vec![],
             head: None,
             tail: None,
             size: size,
         }
     }
 
-    pub fn put(&mut self, k: &K, v: V) {
+    /// Inserts `k`/`v`, returning whatever was displaced: the previous
+    /// value on an overwrite, the evicted tail entry on a capacity
+    /// eviction, or `None` if the cache simply grew.
+    pub fn put(&mut self, k: &K, v: V) -> Option<(K, V)> {
         // check for existing node at t
         let existing = match self.table.get(k) {
             Some((_, i)) => Some(*i),
             None => None,
         };
         if let Some(i) = existing {
-            // update value
-            self.table.insert(k.clone(), (v, i));
-            if let Some(tail) = self.tail {
-                if tail == i {
-                    // if this was the tail, move the tail forward to the next node
-                    self.tail = self.nodes[tail].next
-                }
-            }
-            // matched node follows the prior head and becomes the new head
-            self.nodes[self.head.unwrap()].next = Some(i);
-            self.head = Some(i);
+            // update value and promote to head
+            let (old_v, _) = self.table.insert(k.clone(), (v, i)).unwrap();
+            self.promote(i);
+            Some((k.clone(), old_v))
         } else {
-            if self.nodes.len() < self.size {
+            if self.table.len() < self.size {
                 // haven't filled up storage yet, so just append nodes
                 self.append(k, v);
-                return;
+                return None;
             }
-            let (head, tail) = (self.head.unwrap(), self.tail.unwrap());
-            // remove old tail key from table
-            self.table.remove(&self.nodes[tail].key);
-            // prior head points to tail index
-            self.nodes[head].next = self.tail;
-            // tail updated to the node following prior tail
-            self.tail = self.nodes[tail].next;
-            // tail becomes new head, reusing slot in-place
-            self.head = Some(tail);
-            self.nodes[tail] = Node::new(k.clone());
-            self.table.insert(k.clone(), (v, tail));
+            // at capacity: evict the tail to make room
+            let evicted = self.evict_tail();
+            self.append(k, v);
+            evicted
         }
     }
 
     fn append(&mut self, k: &K, v: V) {
-        if self.nodes.is_empty() {
-            self.nodes.push(Node::new(k.clone()));
-            self.head = Some(0);
-            self.tail = Some(0);
-            self.table.insert(k.clone(), (v, 0));
+        let i = if let Some(i) = self.free.pop() {
+            self.nodes[i] = Node::new(k.clone(), self.head);
+            i
         } else {
-            let new_node = Node {
-                key: k.clone(),
-                next: None,
-            };
-            let new_i = self.nodes.len();
-            self.nodes.push(new_node);
-            self.nodes[self.head.unwrap()].next = Some(new_i);
-            self.head = Some(new_i);
-            self.table.insert(k.clone(), (v, new_i));
+            let i = self.nodes.len();
+            self.nodes.push(Node::new(k.clone(), self.head));
+            i
+        };
+        if let Some(head) = self.head {
+            self.nodes[head].next = Some(i);
+        }
+        self.head = Some(i);
+        if self.tail.is_none() {
+            self.tail = Some(i);
+        }
+        self.table.insert(k.clone(), (v, i));
+    }
+
+    // Unlinks node `i` from the list, patching up its neighbours and
+    // head/tail as needed. Does not touch `table` or `free`.
+    fn unlink(&mut self, i: usize) {
+        let (prev, next) = {
+            let node = &self.nodes[i];
+            (node.prev, node.next)
+        };
+        match prev {
+            Some(p) => self.nodes[p].next = next,
+            None => self.tail = next,
+        }
+        match next {
+            Some(n) => self.nodes[n].prev = prev,
+            None => self.head = prev,
+        }
+    }
+
+    // Moves node `i` to the head of the list (most recently used).
+    fn promote(&mut self, i: usize) {
+        if self.head == Some(i) {
+            return;
+        }
+        self.unlink(i);
+        self.nodes[i].prev = self.head;
+        self.nodes[i].next = None;
+        if let Some(head) = self.head {
+            self.nodes[head].next = Some(i);
+        }
+        self.head = Some(i);
+        if self.tail.is_none() {
+            self.tail = Some(i);
         }
     }
 
+    // Evicts the tail (least recently used) entry, if any, recycling its
+    // slot onto the free list.
+    fn evict_tail(&mut self) -> Option<(K, V)> {
+        let tail = self.tail?;
+        let key = self.nodes[tail].key.clone();
+        let (v, _) = self.table.remove(&key)?;
+        self.unlink(tail);
+        self.free.push(tail);
+        Some((key, v))
+    }
+
     pub fn get(&mut self, k: &K) -> Option<&V> {
         // check if existing node at t
         let i = match self.table.get(k) {
             Some((_, i)) => *i,
             None => return None,
         };
-        // update tail (if matched node was the prior tail)
-        if let Some(tail) = self.tail {
-            if tail == i {
-                self.tail = self.nodes[tail].next
+        self.promote(i);
+        self.table.get(k).map(|(v, _)| v)
+    }
+
+    /// Removes `k` from the cache in O(1), returning its value if present.
+    pub fn remove(&mut self, k: &K) -> Option<V> {
+        let (v, i) = self.table.remove(k)?;
+        self.unlink(i);
+        self.free.push(i);
+        Some(v)
+    }
+
+    /// Returns the value for `k`, promoting it to most-recently-used.
+    /// On a miss, `f` is called to produce the value, which is inserted
+    /// and cloning `k` is deferred until then - a hit never clones.
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, k: &K, f: F) -> &V {
+        match self.table.get(k) {
+            Some((_, i)) => {
+                let i = *i;
+                self.promote(i);
+            }
+            None => {
+                let v = f();
+                if self.table.len() >= self.size {
+                    self.evict_tail();
+                }
+                self.append(k, v);
             }
         }
-        // update head
-        self.nodes[self.head.unwrap()].next = Some(i);
-        self.head = Some(i);
-        self.table.get(k).map(|(v, _)| v)
+        self.table.get(k).map(|(v, _)| v).unwrap()
+    }
+
+    /// Like [`get_or_insert_with`](LRUCache::get_or_insert_with), but `f`
+    /// may fail; on `Err` the cache is left untouched.
+    pub fn try_get_or_insert_with<F, E>(&mut self, k: &K, f: F) -> Result<&V, E>
+    where
+        F: FnOnce() -> Result<V, E>,
+    {
+        match self.table.get(k) {
+            Some((_, i)) => {
+                let i = *i;
+                self.promote(i);
+            }
+            None => {
+                let v = f()?;
+                if self.table.len() >= self.size {
+                    self.evict_tail();
+                }
+                self.append(k, v);
+            }
+        }
+        Ok(self.table.get(k).map(|(v, _)| v).unwrap())
+    }
+
+    /// The number of entries currently in the cache.
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// Removes all entries from the cache.
+    pub fn clear(&mut self) {
+        self.table.clear();
+        self.nodes.clear();
+        self.free.clear();
+        self.head = None;
+        self.tail = None;
+    }
+
+    /// Iterates from most- to least-recently-used without reordering
+    /// anything.
+    pub fn iter(&self) -> Iter<'_, K, V, S> {
+        Iter {
+            cache: self,
+            cur: self.head,
+        }
+    }
+
+    /// Shrinks the cache to `new_size`, evicting LRU entries until it
+    /// fits, or raises the capacity for future `put`s.
+    pub fn resize(&mut self, new_size: usize) {
+        while self.table.len() > new_size {
+            self.evict_tail();
+        }
+        self.size = new_size;
+    }
+}
+
+/// Iterator over `(&K, &V)` from most- to least-recently-used, returned by
+/// [`LRUCache::iter`].
+pub struct Iter<'a, K, V, S> {
+    cache: &'a LRUCache<K, V, S>,
+    cur: Option<usize>,
+}
+
+impl<'a, K, V, S> Iterator for Iter<'a, K, V, S>
+where
+    K: Eq + Hash + Clone,
+    S: BuildHasher,
+{
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let i = self.cur?;
+        let node = &self.cache.nodes[i];
+        self.cur = node.prev;
+        let (v, _) = self.cache.table.get(&node.key).unwrap();
+        Some((&node.key, v))
     }
 }
 
@@ -173,4 +331,148 @@ mod tests {
         assert_eq!(c.get(&"baz".to_string()), None);
         assert_eq!(c.get(&"quux".to_string()), Some(&()));
     }
+
+    #[test]
+    fn test_remove() {
+        let mut c: LRUCache<String, ()> = LRUCache::new(3);
+        c.put(&"foo".to_string(), ());
+        c.put(&"bar".to_string(), ());
+        c.put(&"baz".to_string(), ());
+        assert_eq!(c.remove(&"bar".to_string()), Some(()));
+        assert_eq!(c.get(&"bar".to_string()), None);
+        assert_eq!(c.remove(&"bar".to_string()), None);
+        // removed slot should be reused, not leaked
+        c.put(&"quux".to_string(), ());
+        assert_eq!(c.get(&"foo".to_string()), Some(&()));
+        assert_eq!(c.get(&"baz".to_string()), Some(&()));
+        assert_eq!(c.get(&"quux".to_string()), Some(&()));
+    }
+
+    #[test]
+    fn test_remove_head_and_tail() {
+        let mut c: LRUCache<String, ()> = LRUCache::new(3);
+        c.put(&"foo".to_string(), ());
+        c.put(&"bar".to_string(), ());
+        c.put(&"baz".to_string(), ());
+        // "baz" is head, "foo" is tail
+        assert_eq!(c.remove(&"baz".to_string()), Some(()));
+        assert_eq!(c.remove(&"foo".to_string()), Some(()));
+        assert_eq!(c.get(&"bar".to_string()), Some(&()));
+    }
+
+    #[test]
+    fn test_get_or_insert_with() {
+        let mut c: LRUCache<String, i32> = LRUCache::new(3);
+        let mut misses = 0;
+        assert_eq!(
+            *c.get_or_insert_with(&"foo".to_string(), || {
+                misses += 1;
+                1
+            }),
+            1
+        );
+        assert_eq!(
+            *c.get_or_insert_with(&"foo".to_string(), || {
+                misses += 1;
+                2
+            }),
+            1
+        );
+        assert_eq!(misses, 1);
+    }
+
+    #[test]
+    fn test_try_get_or_insert_with() {
+        let mut c: LRUCache<String, i32> = LRUCache::new(3);
+        let err: Result<&i32, &str> = c.try_get_or_insert_with(&"foo".to_string(), || Err("nope"));
+        assert_eq!(err, Err("nope"));
+        assert_eq!(c.get(&"foo".to_string()), None);
+
+        let ok = c.try_get_or_insert_with(&"foo".to_string(), || Ok::<i32, &str>(1));
+        assert_eq!(ok, Ok(&1));
+        assert_eq!(c.get(&"foo".to_string()), Some(&1));
+    }
+
+    #[test]
+    fn test_put_returns_evicted() {
+        let mut c: LRUCache<String, i32> = LRUCache::new(2);
+        assert_eq!(c.put(&"foo".to_string(), 1), None);
+        assert_eq!(c.put(&"bar".to_string(), 2), None);
+        // overwrite returns the previous value
+        assert_eq!(c.put(&"foo".to_string(), 3), Some(("foo".to_string(), 1)));
+        // capacity eviction returns the evicted tail
+        assert_eq!(
+            c.put(&"baz".to_string(), 4),
+            Some(("bar".to_string(), 2))
+        );
+    }
+
+    #[test]
+    fn test_len_is_empty_clear() {
+        let mut c: LRUCache<String, ()> = LRUCache::new(3);
+        assert_eq!(c.len(), 0);
+        assert!(c.is_empty());
+        c.put(&"foo".to_string(), ());
+        c.put(&"bar".to_string(), ());
+        assert_eq!(c.len(), 2);
+        assert!(!c.is_empty());
+        c.clear();
+        assert_eq!(c.len(), 0);
+        assert!(c.is_empty());
+        assert_eq!(c.get(&"foo".to_string()), None);
+        // slots should be reusable after a clear
+        c.put(&"baz".to_string(), ());
+        assert_eq!(c.get(&"baz".to_string()), Some(&()));
+    }
+
+    #[test]
+    fn test_iter_order() {
+        let mut c: LRUCache<String, i32> = LRUCache::new(3);
+        c.put(&"foo".to_string(), 1);
+        c.put(&"bar".to_string(), 2);
+        c.put(&"baz".to_string(), 3);
+        c.get(&"foo".to_string());
+        // "foo" is now MRU, "bar" is LRU
+        let entries: Vec<(&String, &i32)> = c.iter().collect();
+        assert_eq!(
+            entries,
+            vec![
+                (&"foo".to_string(), &1),
+                (&"baz".to_string(), &3),
+                (&"bar".to_string(), &2),
+            ]
+        );
+        // iterating must not reorder the cache
+        assert_eq!(c.iter().next(), Some((&"foo".to_string(), &1)));
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut c: LRUCache<String, ()> = LRUCache::new(3);
+        c.put(&"foo".to_string(), ());
+        c.put(&"bar".to_string(), ());
+        c.put(&"baz".to_string(), ());
+        c.resize(2);
+        // shrinking evicts from the LRU end
+        assert_eq!(c.get(&"foo".to_string()), None);
+        assert_eq!(c.get(&"bar".to_string()), Some(&()));
+        assert_eq!(c.get(&"baz".to_string()), Some(&()));
+        // growing just raises the bound for future puts
+        c.resize(3);
+        c.put(&"quux".to_string(), ());
+        assert_eq!(c.len(), 3);
+    }
+
+    #[test]
+    fn test_with_hasher() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::BuildHasherDefault;
+
+        let mut c: LRUCache<String, (), BuildHasherDefault<DefaultHasher>> =
+            LRUCache::with_hasher(3, BuildHasherDefault::default());
+        c.put(&"foo".to_string(), ());
+        c.put(&"bar".to_string(), ());
+        assert_eq!(c.get(&"foo".to_string()), Some(&()));
+        assert_eq!(c.get(&"quux".to_string()), None);
+    }
 }